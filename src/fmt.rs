@@ -0,0 +1,28 @@
+//! Logging macros that forward to `defmt` when the `defmt` feature is enabled, and to `log`
+//! otherwise. Pulled in via `#[macro_use]` so every module can call `debug!`/`warn!` directly.
+//!
+//! The two configurations diverge in what they accept: `log`'s `{}` requires `Display`, while
+//! `defmt`'s `{}` requires `defmt::Format` (and has no impl for some types, e.g. function
+//! pointers). `cargo test` only exercises the `defmt`-off path, so a type that derives
+//! `defmt::Format` or a log placeholder that assumes `Display` needs `cargo check --features
+//! defmt` run by hand to catch breakage the default test run won't.
+
+#![allow(unused_macros)]
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "defmt")]
+        defmt::debug!($($arg)*);
+        #[cfg(not(feature = "defmt"))]
+        log::debug!($($arg)*);
+    };
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "defmt")]
+        defmt::warn!($($arg)*);
+        #[cfg(not(feature = "defmt"))]
+        log::warn!($($arg)*);
+    };
+}
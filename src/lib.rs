@@ -0,0 +1,10 @@
+#![cfg_attr(not(test), no_std)]
+
+#[macro_use]
+mod fmt;
+
+mod traits;
+mod updater;
+
+pub use traits::*;
+pub use updater::*;
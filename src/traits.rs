@@ -0,0 +1,64 @@
+use drogue_ajour_protocol::{Command, Status};
+
+/// A snapshot of the device's current firmware state, as reported by
+/// [`FirmwareDevice::status`].
+pub struct FirmwareStatus<'a> {
+    pub current_version: &'a [u8],
+    pub next_offset: u32,
+    pub next_version: Option<&'a [u8]>,
+    /// The release channel (e.g. `"stable"`, `"beta"`, `"canary"`) the device is currently
+    /// subscribed to, if the device tracks one. Used by `FirmwareUpdater` as the outgoing
+    /// channel whenever `FirmwareUpdater::set_channel` has not been called with an override.
+    pub channel: Option<&'a str>,
+}
+
+/// Abstraction over the device-specific parts of the firmware update process: reporting the
+/// current firmware state, writing the incoming image, and triggering the bootloader swap.
+pub trait FirmwareDevice {
+    /// The error type returned by device operations.
+    type Error;
+
+    /// The maximum transfer unit accepted by [`FirmwareDevice::write`].
+    const MTU: usize;
+
+    /// Report the current firmware state of the device.
+    async fn status(&mut self) -> Result<FirmwareStatus<'_>, Self::Error>;
+
+    /// Called once, before the first chunk of a new firmware version is written.
+    async fn start(&mut self, version: &[u8]) -> Result<(), Self::Error>;
+
+    /// Write a chunk of firmware data at the given offset.
+    async fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Swap in the firmware written so far.
+    async fn update(&mut self, version: &[u8], checksum: &[u8]) -> Result<(), Self::Error>;
+
+    /// Called when the device firmware is already up to date.
+    async fn synced(&mut self) -> Result<(), Self::Error>;
+
+    /// Called right after a successful swap, before the updater reports `DeviceStatus::Updated`.
+    ///
+    /// Marks the freshly swapped-in image as tentative, so that a boot which never reaches
+    /// [`FirmwareDevice::confirm`] can be rolled back on the next update check.
+    async fn mark_tentative(&mut self) -> Result<(), Self::Error>;
+
+    /// Confirm that the currently running image is good. Called once the freshly booted
+    /// firmware's self-test passes, via `FirmwareUpdater::confirm`.
+    async fn confirm(&mut self) -> Result<(), Self::Error>;
+
+    /// Report whether the currently running image has been confirmed.
+    async fn is_confirmed(&mut self) -> Result<bool, Self::Error>;
+
+    /// Revert to the previous firmware slot after booting into an unconfirmed tentative image.
+    async fn revert(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Abstraction over the transport used to exchange [`Status`] reports and [`Command`]s with the
+/// update service.
+pub trait UpdateService {
+    /// The error type returned by the transport.
+    type Error;
+
+    /// Report the current status to the service and receive the next command to execute.
+    async fn request<'a>(&'a mut self, status: &'a Status<'a>) -> Result<Command<'a>, Self::Error>;
+}
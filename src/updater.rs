@@ -1,8 +1,88 @@
 use crate::traits::{FirmwareDevice, UpdateService};
+use core::fmt::Debug;
+use core::marker::PhantomData;
 use drogue_ajour_protocol::{Command, Status};
 use embedded_hal_async::delay::DelayUs;
 use heapless::Vec;
 
+/// A firmware version identifier.
+///
+/// Implement this to plug in a semver-aware or fixed-width version type instead of the
+/// default `heapless::Vec<u8, N>`.
+pub trait FirmwareVersion: PartialEq + AsRef<[u8]> + Clone + Debug {
+    /// Construct a version from its wire representation, failing if it doesn't fit.
+    fn from_slice(data: &[u8]) -> Result<Self, ()>;
+}
+
+impl<const N: usize> FirmwareVersion for Vec<u8, N> {
+    fn from_slice(data: &[u8]) -> Result<Self, ()> {
+        Vec::from_slice(data)
+    }
+}
+
+/// Maximum length, in bytes, of a device-reported channel name that the updater will forward.
+/// Longer names are dropped (with a warning) rather than silently reported as no channel.
+const DEVICE_CHANNEL_CAPACITY: usize = 32;
+
+/// Retry/backoff policy applied to consecutive `request` failures before the updater gives up
+/// and surfaces the error to the caller, instead of retrying forever.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Number of consecutive failures after which the updater gives up with `Error::Service`.
+    pub max_attempts: u8,
+    /// Delay before the first retry, in milliseconds.
+    pub base_delay_ms: u32,
+    /// Multiplier applied to the delay after each subsequent failure.
+    pub multiplier: u32,
+    /// Upper bound on the computed delay, in milliseconds.
+    pub max_delay_ms: u32,
+    /// Optional jitter function applied to the computed delay before it is used.
+    pub jitter: Option<fn(u32) -> u32>,
+}
+
+// Hand-written rather than derived: `defmt::Format` has no impl for function pointers, so
+// `jitter` can't be part of a `#[derive(defmt::Format)]` struct. Report whether it's set instead.
+#[cfg(feature = "defmt")]
+impl defmt::Format for RetryPolicy {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "RetryPolicy {{ max_attempts: {}, base_delay_ms: {}, multiplier: {}, max_delay_ms: {}, jitter: {} }}",
+            self.max_attempts,
+            self.base_delay_ms,
+            self.multiplier,
+            self.max_delay_ms,
+            self.jitter.is_some()
+        );
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay_ms: 1_000,
+            multiplier: 2,
+            max_delay_ms: 60_000,
+            jitter: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Compute the backoff delay, in milliseconds, before the `attempt`'th consecutive retry.
+    fn delay_ms(&self, attempt: u8) -> u32 {
+        let mut delay = self.base_delay_ms.min(self.max_delay_ms);
+        for _ in 1..attempt {
+            delay = delay.saturating_mul(self.multiplier).min(self.max_delay_ms);
+        }
+        match self.jitter {
+            Some(jitter) => jitter(delay),
+            None => delay,
+        }
+    }
+}
+
 /// The error types that the updater may return during the update process.
 #[derive(Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -20,30 +100,109 @@ pub enum Error<D, S> {
 pub enum DeviceStatus {
     Synced,
     Updated,
+    /// The device booted into a tentative image that was never confirmed (e.g. the firmware
+    /// crashed or never called [`FirmwareUpdater::confirm`]) and has been reverted to the
+    /// previous slot.
+    Reverted,
+}
+
+/// A coarse-grained progress event emitted by the updater as it advances through the
+/// update protocol. Useful for driving UI, logging, or feeding a watchdog.
+#[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UpdateEvent {
+    /// The updater is checking in with the update service.
+    Checking,
+    /// A firmware chunk is being downloaded. `total` is the full image size when known.
+    Downloading { offset: u32, total: Option<u32> },
+    /// The device is swapping in the newly downloaded firmware.
+    Swapping,
+    /// The device firmware is already up to date.
+    Synced,
+    /// The device firmware has been swapped and is ready to reboot into the new image.
+    Updated,
+    /// The device booted into an unconfirmed tentative image and has been reverted to the
+    /// previous slot.
+    Reverted,
+    /// The updater is waiting before the next check-in, either as instructed by the service or
+    /// while backing off after a failed request. `poll` is the wait in whole seconds (rounded
+    /// down), when known.
+    WaitingToRetry { poll: Option<u32> },
+}
+
+/// Observer that is notified as the updater progresses through the update protocol.
+///
+/// Implement this to drive UI, logging, or watchdog feeding without forking the updater.
+/// A no-op implementation is provided for `()` so passing a monitor remains optional.
+pub trait UpdateMonitor {
+    async fn on_event(&mut self, event: UpdateEvent);
+}
+
+impl UpdateMonitor for () {
+    async fn on_event(&mut self, _event: UpdateEvent) {}
 }
 
-struct UpdaterState {
-    current_version: Vec<u8, 32>,
+struct UpdaterState<V> {
+    current_version: V,
     next_offset: u32,
-    next_version: Option<Vec<u8, 32>>,
+    next_version: Option<V>,
+    /// Number of consecutive `request` failures seen so far, reset on any success.
+    failures: u8,
 }
 
 /// The updater process that uses the update service to perform a firmware update check
 /// for a device. If the device needs to be updated, the updater will follow the update protocol
-pub struct FirmwareUpdater<T>
+pub struct FirmwareUpdater<T, V = Vec<u8, 32>>
 where
     T: UpdateService,
+    V: FirmwareVersion,
 {
     service: T,
+    channel: Option<heapless::String<DEVICE_CHANNEL_CAPACITY>>,
+    retry: RetryPolicy,
+    _version: PhantomData<V>,
 }
 
-impl<T> FirmwareUpdater<T>
+impl<T, V> FirmwareUpdater<T, V>
 where
     T: UpdateService,
+    V: FirmwareVersion,
 {
     /// Create a new instance of the updater with the provided service instance.
     pub fn new(service: T) -> Self {
-        Self { service }
+        Self {
+            service,
+            channel: None,
+            retry: RetryPolicy::default(),
+            _version: PhantomData,
+        }
+    }
+
+    /// Set the release channel (e.g. `"stable"`, `"beta"`, `"canary"`) to advertise in status
+    /// reports, so the update service can resolve the correct artifact for this rollout track.
+    ///
+    /// `channel` is copied into an owned, fixed-capacity buffer, so it may come from a runtime
+    /// source (a device-id hash, a value read from NVS) rather than needing a `'static` lifetime.
+    /// A channel longer than `DEVICE_CHANNEL_CAPACITY` bytes is dropped, with a warning, the same
+    /// as an oversized device-reported channel.
+    pub fn set_channel(&mut self, channel: Option<&str>) {
+        self.channel = channel.and_then(|c| {
+            let mut buf = heapless::String::new();
+            if buf.push_str(c).is_ok() {
+                Some(buf)
+            } else {
+                warn!(
+                    "Channel {:?} exceeds {} bytes, ignoring",
+                    c, DEVICE_CHANNEL_CAPACITY
+                );
+                None
+            }
+        });
+    }
+
+    /// Set the retry/backoff policy applied to consecutive `request` failures.
+    pub fn set_retry_policy(&mut self, retry: RetryPolicy) {
+        self.retry = retry;
     }
 
     async fn check<F: FirmwareDevice, D: DelayUs>(
@@ -51,19 +210,50 @@ where
         device: &mut F,
         delay: &mut D,
     ) -> Result<bool, Error<F::Error, T::Error>> {
+        self.check_with_monitor(device, delay, &mut ()).await
+    }
+
+    async fn check_with_monitor<F: FirmwareDevice, D: DelayUs, M: UpdateMonitor>(
+        &mut self,
+        device: &mut F,
+        delay: &mut D,
+        monitor: &mut M,
+    ) -> Result<bool, Error<F::Error, T::Error>> {
+        monitor.on_event(UpdateEvent::Checking).await;
+        let mut device_channel: Option<heapless::String<DEVICE_CHANNEL_CAPACITY>> = None;
         let mut state = {
             let initial = device.status().await.map_err(|e| Error::Device(e))?;
-            UpdaterState {
-                current_version: Vec::from_slice(initial.current_version)
+            debug!("Device channel: {:?}", initial.channel);
+            if let Some(c) = initial.channel {
+                let mut buf = heapless::String::new();
+                if buf.push_str(c).is_ok() {
+                    device_channel = Some(buf);
+                } else {
+                    warn!(
+                        "Device channel {:?} exceeds {} bytes, ignoring",
+                        c, DEVICE_CHANNEL_CAPACITY
+                    );
+                }
+            }
+            UpdaterState::<V> {
+                current_version: V::from_slice(initial.current_version)
                     .map_err(|_| Error::Encode)?,
                 next_offset: initial.next_offset,
                 next_version: if let Some(next_version) = &initial.next_version {
-                    Some(Vec::from_slice(next_version).map_err(|_| Error::Encode)?)
+                    Some(V::from_slice(next_version).map_err(|_| Error::Encode)?)
                 } else {
                     None
                 },
+                failures: 0,
             }
         };
+        // Prefer the explicitly configured channel; fall back to whatever the device itself
+        // reports it is subscribed to.
+        let channel = self
+            .channel
+            .as_ref()
+            .map(|c| c.as_str())
+            .or_else(|| device_channel.as_ref().map(|c| c.as_str()));
 
         #[allow(unused_mut)]
         #[allow(unused_assignments)]
@@ -72,14 +262,14 @@ where
         loop {
             let status = if let Some(next) = &state.next_version {
                 Status::update(
-                    &state.current_version,
+                    state.current_version.as_ref(),
                     Some(F::MTU as u32),
                     state.next_offset,
-                    next,
-                    None,
+                    next.as_ref(),
+                    channel,
                 )
             } else {
-                Status::first(&state.current_version, Some(F::MTU as u32), None)
+                Status::first(state.current_version.as_ref(), Some(F::MTU as u32), channel)
             };
 
             debug!("Sending status: {:?}", status);
@@ -88,6 +278,9 @@ where
                 .service
                 .request(&status)
                 .await;
+            if cmd.is_ok() {
+                state.failures = 0;
+            }
             match cmd {
                 Ok(Command::Write {
                     version,
@@ -97,7 +290,7 @@ where
                 }) => {
                     if offset == 0 {
                         debug!(
-                            "Updating device firmware from {} to {}",
+                            "Updating device firmware from {:?} to {:?}",
                             state.current_version,
                             version.as_ref()
                         );
@@ -113,7 +306,13 @@ where
                     state.next_offset += data.len() as u32;
                     state
                         .next_version
-                        .replace(Vec::from_slice(version.as_ref()).map_err(|_| Error::Decode)?);
+                        .replace(V::from_slice(version.as_ref()).map_err(|_| Error::Decode)?);
+                    monitor
+                        .on_event(UpdateEvent::Downloading {
+                            offset,
+                            total: None,
+                        })
+                        .await;
                 }
                 Ok(Command::Sync {
                     version: _,
@@ -122,6 +321,7 @@ where
                 }) => {
                     debug!("Device firmware is up to date");
                     device.synced().await.map_err(|e| Error::Device(e))?;
+                    monitor.on_event(UpdateEvent::Synced).await;
                     return Ok(true);
                 }
                 Ok(Command::Wait {
@@ -129,6 +329,7 @@ where
                     correlation_id: _,
                 }) => {
                     debug!("Instruction to wait for {:?} seconds", poll);
+                    monitor.on_event(UpdateEvent::WaitingToRetry { poll }).await;
                     if let Some(poll) = poll {
                         delay
                             .delay_ms(poll * 1000)
@@ -142,10 +343,13 @@ where
                     correlation_id: _,
                 }) => {
                     debug!("Swaping firmware");
+                    monitor.on_event(UpdateEvent::Swapping).await;
                     device
                         .update(version.as_ref(), checksum.as_ref())
                         .await
                         .map_err(|e| Error::Device(e))?;
+                    device.mark_tentative().await.map_err(|e| Error::Device(e))?;
+                    monitor.on_event(UpdateEvent::Updated).await;
                     return Ok(false);
                 }
                 Err(e) => {
@@ -153,64 +357,110 @@ where
                     debug!("Error reporting status: {:?}", defmt::Debug2Format(&e));
                     #[cfg(not(feature = "defmt"))]
                     debug!("Error reporting status: {:?}", e);
+
+                    state.failures += 1;
+                    if state.failures >= self.retry.max_attempts {
+                        warn!("Giving up after {} consecutive failures", state.failures);
+                        return Err(Error::Service(e));
+                    }
+                    let wait_ms = self.retry.delay_ms(state.failures);
+                    monitor
+                        .on_event(UpdateEvent::WaitingToRetry {
+                            poll: Some(wait_ms / 1000),
+                        })
+                        .await;
+                    delay.delay_ms(wait_ms).await.map_err(|_| Error::Delay)?;
                 }
             }
         }
     }
 
-    /// Run the firmware update protocol. The update is finished with two outcomes:
+    /// Run the firmware update protocol. Before contacting the update service, this checks
+    /// whether the device booted into a tentative, unconfirmed image; if so the device is rolled
+    /// back immediately. Otherwise, the update check finishes with one of two outcomes:
     ///
     /// 1) The device is in sync, in which case `DeviceStatus::Synced` is returned.
     /// 2) The device is updated, in which case `DeviceStatus::Updated` is returned. It is the responsibility
     ///    of called to reset the device in order to run the new firmware.
+    ///
+    /// A third outcome, `DeviceStatus::Reverted`, is returned instead of either of the above if
+    /// the device was just rolled back to its previous slot; see [`FirmwareDevice::confirm`] and
+    /// [`FirmwareUpdater::confirm`].
     pub async fn run<F: FirmwareDevice, D: DelayUs>(
         &mut self,
         device: &mut F,
         delay: &mut D,
     ) -> Result<DeviceStatus, Error<F::Error, T::Error>> {
-        if self.check(device, delay).await? {
+        self.run_with_monitor(device, delay, &mut ()).await
+    }
+
+    /// Run the firmware update protocol, reporting progress to the given `monitor` as the
+    /// protocol loop advances. See [`run`](Self::run) for the meaning of the returned status.
+    pub async fn run_with_monitor<F: FirmwareDevice, D: DelayUs, M: UpdateMonitor>(
+        &mut self,
+        device: &mut F,
+        delay: &mut D,
+        monitor: &mut M,
+    ) -> Result<DeviceStatus, Error<F::Error, T::Error>> {
+        if !device.is_confirmed().await.map_err(|e| Error::Device(e))? {
+            debug!("Booted into an unconfirmed image, reverting");
+            device.revert().await.map_err(|e| Error::Device(e))?;
+            monitor.on_event(UpdateEvent::Reverted).await;
+            return Ok(DeviceStatus::Reverted);
+        }
+
+        if self.check_with_monitor(device, delay, monitor).await? {
             Ok(DeviceStatus::Synced)
         } else {
             Ok(DeviceStatus::Updated)
         }
     }
+
+    /// Confirm that the currently running firmware is good. The freshly booted firmware should
+    /// call this once its self-test passes, to prevent the next update check from rolling it
+    /// back to the previous slot.
+    pub async fn confirm<F: FirmwareDevice>(
+        &mut self,
+        device: &mut F,
+    ) -> Result<(), Error<F::Error, T::Error>> {
+        device.confirm().await.map_err(|e| Error::Device(e))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use core::convert::Infallible;
-    use core::future::Future;
+
+    use drogue_ajour_protocol::{Command, Status};
 
     use crate::DeviceStatus;
+    use crate::Error;
+    use crate::FirmwareDevice;
+    use crate::FirmwareStatus;
     use crate::FirmwareUpdater;
+    use crate::FirmwareVersion;
     use crate::InMemory;
+    use crate::RetryPolicy;
     use crate::Simulator;
+    use crate::UpdateEvent;
+    use crate::UpdateMonitor;
+    use crate::UpdateService;
+
+    use super::DEVICE_CHANNEL_CAPACITY;
 
     pub struct TokioDelay;
 
     impl embedded_hal_async::delay::DelayUs for TokioDelay {
         type Error = Infallible;
 
-        type DelayUsFuture<'a> = impl Future<Output = Result<(), Self::Error>>
-        where
-            Self: 'a;
-
-        fn delay_us(&mut self, us: u32) -> Self::DelayUsFuture<'_> {
-            async move {
-                tokio::time::sleep(tokio::time::Duration::from_micros(us as u64)).await;
-                Ok(())
-            }
+        async fn delay_us(&mut self, us: u32) -> Result<(), Self::Error> {
+            tokio::time::sleep(tokio::time::Duration::from_micros(us as u64)).await;
+            Ok(())
         }
 
-        type DelayMsFuture<'a> = impl Future<Output = Result<(), Self::Error>>
-        where
-            Self: 'a;
-
-        fn delay_ms(&mut self, ms: u32) -> Self::DelayMsFuture<'_> {
-            async move {
-                tokio::time::sleep(tokio::time::Duration::from_millis(ms as u64)).await;
-                Ok(())
-            }
+        async fn delay_ms(&mut self, ms: u32) -> Result<(), Self::Error> {
+            tokio::time::sleep(tokio::time::Duration::from_millis(ms as u64)).await;
+            Ok(())
         }
     }
 
@@ -233,4 +483,436 @@ mod tests {
         let status = updater.run(&mut device, &mut TokioDelay).await.unwrap();
         assert_eq!(status, DeviceStatus::Updated);
     }
+
+    struct RecordingMonitor {
+        events: std::vec::Vec<UpdateEvent>,
+    }
+
+    impl UpdateMonitor for RecordingMonitor {
+        async fn on_event(&mut self, event: UpdateEvent) {
+            self.events.push(event);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_protocol_reports_progress() {
+        let service = InMemory::new(b"2", &[1; 1024]);
+        let mut device = Simulator::new(b"1");
+        let mut monitor = RecordingMonitor {
+            events: std::vec::Vec::new(),
+        };
+
+        let mut updater = FirmwareUpdater::new(service);
+        let status = updater
+            .run_with_monitor(&mut device, &mut TokioDelay, &mut monitor)
+            .await
+            .unwrap();
+        assert_eq!(status, DeviceStatus::Updated);
+
+        assert_eq!(monitor.events.first(), Some(&UpdateEvent::Checking));
+        assert!(monitor
+            .events
+            .iter()
+            .any(|e| matches!(e, UpdateEvent::Downloading { .. })));
+        assert_eq!(monitor.events.last(), Some(&UpdateEvent::Updated));
+    }
+
+    struct MockDevice {
+        current_version: &'static [u8],
+        channel: Option<&'static str>,
+        confirmed: bool,
+        reverted: bool,
+        mark_tentative_called: bool,
+    }
+
+    impl MockDevice {
+        fn new(confirmed: bool) -> Self {
+            Self {
+                current_version: b"1",
+                channel: None,
+                confirmed,
+                reverted: false,
+                mark_tentative_called: false,
+            }
+        }
+
+        fn with_channel(mut self, channel: Option<&'static str>) -> Self {
+            self.channel = channel;
+            self
+        }
+
+        fn with_version(mut self, version: &'static [u8]) -> Self {
+            self.current_version = version;
+            self
+        }
+    }
+
+    impl FirmwareDevice for MockDevice {
+        type Error = Infallible;
+
+        const MTU: usize = 128;
+
+        async fn status(&mut self) -> Result<FirmwareStatus<'_>, Self::Error> {
+            Ok(FirmwareStatus {
+                current_version: self.current_version,
+                next_offset: 0,
+                next_version: None,
+                channel: self.channel,
+            })
+        }
+
+        async fn start(&mut self, _version: &[u8]) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn write(&mut self, _offset: u32, _data: &[u8]) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn update(&mut self, _version: &[u8], _checksum: &[u8]) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn synced(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn mark_tentative(&mut self) -> Result<(), Self::Error> {
+            self.mark_tentative_called = true;
+            Ok(())
+        }
+
+        async fn confirm(&mut self) -> Result<(), Self::Error> {
+            self.confirmed = true;
+            Ok(())
+        }
+
+        async fn is_confirmed(&mut self) -> Result<bool, Self::Error> {
+            Ok(self.confirmed)
+        }
+
+        async fn revert(&mut self) -> Result<(), Self::Error> {
+            self.reverted = true;
+            Ok(())
+        }
+    }
+
+    struct UnreachableService;
+
+    impl UpdateService for UnreachableService {
+        type Error = ();
+
+        async fn request<'a>(
+            &'a mut self,
+            _status: &'a Status<'a>,
+        ) -> Result<Command<'a>, Self::Error> {
+            unreachable!("service must not be contacted before the device is confirmed")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reverts_unconfirmed_image_without_contacting_service() {
+        let mut device = MockDevice::new(false);
+        let mut updater = FirmwareUpdater::new(UnreachableService);
+        let mut monitor = RecordingMonitor {
+            events: std::vec::Vec::new(),
+        };
+
+        let status = updater
+            .run_with_monitor(&mut device, &mut TokioDelay, &mut monitor)
+            .await
+            .unwrap();
+        assert_eq!(status, DeviceStatus::Reverted);
+        assert!(device.reverted);
+        assert_eq!(monitor.events, std::vec![UpdateEvent::Reverted]);
+    }
+
+    struct SwapOnceService;
+
+    impl UpdateService for SwapOnceService {
+        type Error = ();
+
+        async fn request<'a>(
+            &'a mut self,
+            _status: &'a Status<'a>,
+        ) -> Result<Command<'a>, Self::Error> {
+            Ok(Command::Swap {
+                version: b"2",
+                checksum: b"checksum",
+                correlation_id: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_device_swaps_and_marks_tentative() {
+        let mut device = MockDevice::new(true);
+        let mut updater = FirmwareUpdater::new(SwapOnceService);
+
+        let status = updater.run(&mut device, &mut TokioDelay).await.unwrap();
+        assert_eq!(status, DeviceStatus::Updated);
+        assert!(device.mark_tentative_called);
+    }
+
+    #[derive(Clone, PartialEq, Debug)]
+    struct FixedVersion([u8; 4]);
+
+    impl AsRef<[u8]> for FixedVersion {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    impl FirmwareVersion for FixedVersion {
+        fn from_slice(data: &[u8]) -> Result<Self, ()> {
+            data.try_into().map(FixedVersion).map_err(|_| ())
+        }
+    }
+
+    struct WriteThenSyncService {
+        calls: std::rc::Rc<core::cell::Cell<u8>>,
+    }
+
+    impl UpdateService for WriteThenSyncService {
+        type Error = ();
+
+        async fn request<'a>(
+            &'a mut self,
+            _status: &'a Status<'a>,
+        ) -> Result<Command<'a>, Self::Error> {
+            let call = self.calls.get();
+            self.calls.set(call + 1);
+            match call {
+                0 => Ok(Command::Write {
+                    version: b"v002",
+                    offset: 0,
+                    data: b"firmware",
+                    correlation_id: None,
+                }),
+                _ => Ok(Command::Sync {
+                    version: b"v002",
+                    poll: None,
+                    correlation_id: None,
+                }),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_firmware_version_round_trips_through_updater() {
+        let mut device = MockDevice::new(true).with_version(b"v001");
+        let calls = std::rc::Rc::new(core::cell::Cell::new(0u8));
+        let mut updater: FirmwareUpdater<_, FixedVersion> =
+            FirmwareUpdater::new(WriteThenSyncService {
+                calls: calls.clone(),
+            });
+
+        let status = updater.run(&mut device, &mut TokioDelay).await.unwrap();
+        assert_eq!(status, DeviceStatus::Synced);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_delay_ms_grows_with_multiplier_and_caps_at_max() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay_ms: 100,
+            multiplier: 2,
+            max_delay_ms: 1_000,
+            jitter: None,
+        };
+
+        assert_eq!(policy.delay_ms(1), 100);
+        assert_eq!(policy.delay_ms(2), 200);
+        assert_eq!(policy.delay_ms(3), 400);
+        assert_eq!(policy.delay_ms(4), 800);
+        assert_eq!(policy.delay_ms(5), 1_000);
+        assert_eq!(policy.delay_ms(6), 1_000);
+    }
+
+    #[test]
+    fn test_delay_ms_applies_jitter() {
+        fn add_one_ms(ms: u32) -> u32 {
+            ms + 1
+        }
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay_ms: 100,
+            multiplier: 2,
+            max_delay_ms: 1_000,
+            jitter: Some(add_one_ms),
+        };
+
+        assert_eq!(policy.delay_ms(1), 101);
+        assert_eq!(policy.delay_ms(3), 401);
+    }
+
+    struct AlwaysErrorService;
+
+    impl UpdateService for AlwaysErrorService {
+        type Error = ();
+
+        async fn request<'a>(
+            &'a mut self,
+            _status: &'a Status<'a>,
+        ) -> Result<Command<'a>, Self::Error> {
+            Err(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let mut device = MockDevice::new(true);
+        let mut updater = FirmwareUpdater::new(AlwaysErrorService);
+        updater.set_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            base_delay_ms: 1,
+            multiplier: 1,
+            max_delay_ms: 1,
+            jitter: None,
+        });
+
+        let result = updater.run(&mut device, &mut TokioDelay).await;
+        assert!(matches!(result, Err(Error::Service(()))));
+    }
+
+    struct FlakyService {
+        calls: std::rc::Rc<core::cell::Cell<u8>>,
+    }
+
+    impl UpdateService for FlakyService {
+        type Error = ();
+
+        async fn request<'a>(
+            &'a mut self,
+            _status: &'a Status<'a>,
+        ) -> Result<Command<'a>, Self::Error> {
+            let call = self.calls.get();
+            self.calls.set(call + 1);
+            match call {
+                // Fail once, then succeed with a `Wait` (which does not end the loop), so a
+                // correctly-resetting failure counter needs three more failures to give up.
+                0 => Err(()),
+                1 => Ok(Command::Wait {
+                    poll: Some(0),
+                    correlation_id: None,
+                }),
+                _ => Err(()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resets_failure_count_on_success() {
+        let mut device = MockDevice::new(true);
+        let calls = std::rc::Rc::new(core::cell::Cell::new(0u8));
+        let mut updater = FirmwareUpdater::new(FlakyService {
+            calls: calls.clone(),
+        });
+        updater.set_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            base_delay_ms: 1,
+            multiplier: 1,
+            max_delay_ms: 1,
+            jitter: None,
+        });
+
+        let result = updater.run(&mut device, &mut TokioDelay).await;
+        assert!(matches!(result, Err(Error::Service(()))));
+        // Call 0 fails, call 1 succeeds (resetting the counter), calls 2-4 fail and trigger
+        // give-up. Without the reset, give-up would have happened one call earlier.
+        assert_eq!(calls.get(), 5);
+    }
+
+    struct RecordingService {
+        channel: std::rc::Rc<core::cell::RefCell<Option<std::string::String>>>,
+    }
+
+    impl UpdateService for RecordingService {
+        type Error = ();
+
+        async fn request<'a>(
+            &'a mut self,
+            status: &'a Status<'a>,
+        ) -> Result<Command<'a>, Self::Error> {
+            *self.channel.borrow_mut() = status.channel.map(|c| c.into());
+            Ok(Command::Sync {
+                version: b"1",
+                poll: None,
+                correlation_id: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_channel_overrides_device_channel() {
+        let mut device = MockDevice::new(true).with_channel(Some("beta"));
+        let channel = std::rc::Rc::new(core::cell::RefCell::new(None));
+        let mut updater = FirmwareUpdater::new(RecordingService {
+            channel: channel.clone(),
+        });
+        updater.set_channel(Some("stable"));
+
+        updater.run(&mut device, &mut TokioDelay).await.unwrap();
+        assert_eq!(channel.borrow().as_deref(), Some("stable"));
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_device_reported_channel() {
+        let mut device = MockDevice::new(true).with_channel(Some("beta"));
+        let channel = std::rc::Rc::new(core::cell::RefCell::new(None));
+        let mut updater = FirmwareUpdater::new(RecordingService {
+            channel: channel.clone(),
+        });
+
+        updater.run(&mut device, &mut TokioDelay).await.unwrap();
+        assert_eq!(channel.borrow().as_deref(), Some("beta"));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_device_channel_is_dropped_not_forwarded() {
+        let oversized: &'static str = "x".repeat(DEVICE_CHANNEL_CAPACITY + 1).leak();
+        let mut device = MockDevice::new(true).with_channel(Some(oversized));
+        let channel = std::rc::Rc::new(core::cell::RefCell::new(None));
+        let mut updater = FirmwareUpdater::new(RecordingService {
+            channel: channel.clone(),
+        });
+
+        updater.run(&mut device, &mut TokioDelay).await.unwrap();
+        assert_eq!(channel.borrow().as_deref(), None);
+    }
+
+    #[tokio::test]
+    async fn test_set_channel_accepts_non_static_str() {
+        let mut device = MockDevice::new(true);
+        let channel = std::rc::Rc::new(core::cell::RefCell::new(None));
+        let mut updater = FirmwareUpdater::new(RecordingService {
+            channel: channel.clone(),
+        });
+
+        // A channel resolved at runtime (e.g. from a device-id hash or an NVS read) only lives
+        // as long as this block, not 'static; `set_channel` must copy it rather than borrow it.
+        {
+            let resolved = std::format!("device-{}", 42);
+            updater.set_channel(Some(&resolved));
+        }
+
+        updater.run(&mut device, &mut TokioDelay).await.unwrap();
+        assert_eq!(channel.borrow().as_deref(), Some("device-42"));
+    }
+
+    #[tokio::test]
+    async fn test_set_channel_drops_oversized_channel() {
+        let mut device = MockDevice::new(true);
+        let channel = std::rc::Rc::new(core::cell::RefCell::new(None));
+        let mut updater = FirmwareUpdater::new(RecordingService {
+            channel: channel.clone(),
+        });
+
+        let oversized = "x".repeat(DEVICE_CHANNEL_CAPACITY + 1);
+        updater.set_channel(Some(&oversized));
+
+        updater.run(&mut device, &mut TokioDelay).await.unwrap();
+        assert_eq!(channel.borrow().as_deref(), None);
+    }
 }